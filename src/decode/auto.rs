@@ -0,0 +1,160 @@
+//! Auto-detecting decompression, for callers that don't know in advance whether a stream
+//! is `.lzma`, LZMA2, or `.xz` (the way `dpkg` or `cygwin-setup` route a stream to the
+//! right decoder by peeking at its header instead of trusting a filename).
+
+use std::io;
+
+use crate::error;
+
+/// The container format [`auto_decompress`] detected and decoded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    /// A `.xz` stream, detected via its 6-byte magic.
+    Xz,
+    /// A legacy headered `.lzma` stream.
+    Lzma,
+    /// A headerless LZMA2 stream.
+    Lzma2,
+}
+
+/// Sniff the container format from the leading bytes of `input` without consuming them,
+/// then decode it into `output`. Returns which format was chosen.
+pub fn auto_decompress<R: io::BufRead, W: io::Write>(
+    input: &mut R,
+    output: &mut W,
+) -> error::Result<Format> {
+    let format = sniff_format(input)?;
+    match format {
+        Format::Xz => crate::xz_decompress(input, output)?,
+        Format::Lzma => crate::lzma_decompress(input, output)?,
+        Format::Lzma2 => crate::lzma2_decompress(input, output)?,
+    }
+    Ok(format)
+}
+
+/// Peek at `input`'s leading bytes (via [`BufRead::fill_buf`]) and decide which format
+/// they belong to, without consuming anything.
+fn sniff_format<R: io::BufRead>(input: &mut R) -> error::Result<Format> {
+    let buf = input.fill_buf().map_err(error::Error::HeaderTooShort)?;
+
+    if buf.starts_with(&crate::xz::HEADER_MAGIC) {
+        return Ok(Format::Xz);
+    }
+
+    if looks_like_lzma_header(buf) {
+        return Ok(Format::Lzma);
+    }
+
+    if let Some(&control) = buf.first() {
+        // LZMA2 chunk control byte: 0x00 end-of-stream, 0x01/0x02 uncompressed chunk,
+        // 0x80..=0xff LZMA chunk (reset flags in the low bits).
+        if control == 0x00 || control == 0x01 || control == 0x02 || control >= 0x80 {
+            return Ok(Format::Lzma2);
+        }
+    }
+
+    Err(error::Error::LzmaError(
+        "could not detect LZMA/LZMA2/XZ container format from header bytes".to_string(),
+    ))
+}
+
+/// Check `buf` against the shape of a genuine 13-byte `.lzma` header (1-byte props,
+/// 4-byte LE dict size, 8-byte LE uncompressed size or the all-`0xff` "unknown" marker).
+///
+/// A real LZMA2 stream commonly starts with a control byte in `0x80..=0xe0`, which alone
+/// satisfies `props < 225`, so checking only the first 5 bytes (as an earlier version of
+/// this function did) misidentifies most real LZMA2 streams as legacy `.lzma`: the next 4
+/// bytes of compressed LZMA2 data just happen to look like a plausible dict size often
+/// enough to matter. Requiring the dict size to match the shape that
+/// [`crate::encode::options::Options`] actually produces (`2 << i`/`3 << i` for `i` in
+/// `11..=30`), and the uncompressed size field that follows it to be sane too, makes a
+/// false match on high-entropy LZMA2 data astronomically unlikely.
+fn looks_like_lzma_header(buf: &[u8]) -> bool {
+    if buf.len() < 13 {
+        return false;
+    }
+    let props = buf[0];
+    let dict_size = u32::from_le_bytes([buf[1], buf[2], buf[3], buf[4]]);
+    let uncompressed_size = u64::from_le_bytes([
+        buf[5], buf[6], buf[7], buf[8], buf[9], buf[10], buf[11], buf[12],
+    ]);
+    props < 225
+        && is_plausible_dict_size(dict_size)
+        && (uncompressed_size == u64::MAX || uncompressed_size < (1 << 48))
+}
+
+/// True if `dict_size` is a `2 << i` or `3 << i` value for `i` in `11..=30`, the only
+/// shapes a real LZMA dictionary size encoder (reference or this crate's
+/// [`crate::encode::options`]) ever produces.
+fn is_plausible_dict_size(dict_size: u32) -> bool {
+    let dict_size = u64::from(dict_size);
+    (11..=30u32).any(|i| dict_size == 2 << i || dict_size == 3 << i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lzma_header(props: u8, dict_size: u32, uncompressed_size: u64) -> Vec<u8> {
+        let mut buf = vec![props];
+        buf.extend_from_slice(&dict_size.to_le_bytes());
+        buf.extend_from_slice(&uncompressed_size.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn detects_xz_magic_first() {
+        let mut buf = crate::xz::HEADER_MAGIC.to_vec();
+        buf.extend_from_slice(&[0; 20]);
+        assert_eq!(sniff_format(&mut io::Cursor::new(buf)).unwrap(), Format::Xz);
+    }
+
+    #[test]
+    fn detects_genuine_lzma_header() {
+        for &dict_size in &[1u32 << 14, 1 << 16, 1 << 25, 3 << 20] {
+            let buf = lzma_header(0x5d, dict_size, u64::MAX);
+            assert!(
+                looks_like_lzma_header(&buf),
+                "dict_size {} should look like a real .lzma header",
+                dict_size
+            );
+            assert_eq!(
+                sniff_format(&mut io::Cursor::new(buf)).unwrap(),
+                Format::Lzma
+            );
+        }
+
+        // A known uncompressed size (rather than the "unknown" 0xff...ff marker) is just
+        // as valid, as long as it's within a sane range.
+        let buf = lzma_header(0x5d, 1 << 16, 12_345);
+        assert!(looks_like_lzma_header(&buf));
+    }
+
+    #[test]
+    fn does_not_misdetect_lzma2_chunks_as_lzma() {
+        // Real-looking LZMA2 streams: a reset-flagged LZMA chunk control byte (0x80..=0xe0)
+        // followed by high-entropy compressed data. `props < 225` alone is satisfied by
+        // every one of these control bytes, which is exactly the bug under test.
+        let cases: &[[u8; 13]] = &[
+            [0xe0, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x11, 0x22, 0x33, 0x44],
+            [0x80, 0x00, 0x01, 0xff, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08],
+            [0xa1, 0x7f, 0x80, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a],
+        ];
+        for buf in cases {
+            assert!(
+                !looks_like_lzma_header(buf),
+                "{:?} should not look like a genuine .lzma header",
+                buf
+            );
+            assert_eq!(
+                sniff_format(&mut io::Cursor::new(*buf)).unwrap(),
+                Format::Lzma2
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_short_buffers() {
+        assert!(!looks_like_lzma_header(&lzma_header(0, 1 << 16, 0)[..12]));
+    }
+}
@@ -4,6 +4,39 @@ use byteorder::{ReadBytesExt, LittleEndian};
 
 use super::options::Options;
 
+/// The lc/lp/pb triple packed into an LZMA header's single properties byte.
+#[derive(Clone, Copy, Debug)]
+pub struct LzmaProperties {
+    /// most lc significant bits of previous byte are part of the literal context
+    pub lc: u32, // 0..8
+    /// literal position of lzma file
+    pub lp: u32, // 0..4
+    /// context for literal/match is plaintext offset modulo 2^pb
+    pub pb: u32, // 0..4
+}
+
+impl LzmaProperties {
+    /// Decode the lc/lp/pb triple packed into a single LZMA properties byte.
+    pub fn decode(byte: u8) -> error::Result<LzmaProperties> {
+        let mut d = byte as u32;
+        if d >= 9 * 5 * 5 {
+            return Err(error::Error::LzmaError(format!(
+                "LZMA header invalid properties: {} must be < {}",
+                d,
+                9 * 5 * 5
+            )));
+        }
+
+        let lc = d % 9;
+        d /= 9;
+        let lp = d % 5;
+        d /= 5;
+        let pb = d;
+
+        Ok(LzmaProperties { lc, lp, pb })
+    }
+}
+
 ///Parameters that describe how the Lzma file is structured
 #[derive(Debug)]
 pub struct LzmaParams {
@@ -20,6 +53,26 @@ pub struct LzmaParams {
 }
 
 impl LzmaParams {
+    /// Build parameters directly from already-known properties, without reading a header.
+    ///
+    /// Useful for headerless/raw LZMA streams (see [`crate::decompress::raw`]) where the
+    /// `lc`/`lp`/`pb`, dictionary size, and unpacked size come from the surrounding
+    /// container format instead of a 13-byte `.lzma` header.
+    ///
+    /// `dict_size` is floored to `0x1000` the same way [`read_header`](Self::read_header)
+    /// floors the one it reads, so a raw caller (or a malformed container) supplying `0`
+    /// or some other tiny value can't sail past the circular buffer's minimum size.
+    pub fn new(properties: LzmaProperties, dict_size: u32, unpacked_size: Option<u64>) -> Self {
+        let dict_size = if dict_size < 0x1000 { 0x1000 } else { dict_size };
+        LzmaParams {
+            lc: properties.lc,
+            lp: properties.lp,
+            pb: properties.pb,
+            dict_size,
+            unpacked_size,
+        }
+    }
+
     /// read header of a lzma file
     pub fn read_header<R>(input: &mut R, options: &Options) -> error::Result<LzmaParams>
     where
@@ -27,19 +80,7 @@ impl LzmaParams {
     {
         // Properties
         let props = input.read_u8().map_err(error::Error::HeaderTooShort)?;
-
-        let mut pb = props as u32;
-        if pb >= 225 {
-            return Err(error::Error::LzmaError(format!(
-                "LZMA header invalid properties: {} must be < 225",
-                pb
-            )));
-        }
-
-        let lc: u32 = pb % 9;
-        pb /= 9;
-        let lp: u32 = pb % 5;
-        pb /= 5;
+        let LzmaProperties { lc, lp, pb } = LzmaProperties::decode(props)?;
 
         lzma_info!("Properties {{ lc: {}, lp: {}, pb: {} }}", lc, lp, pb);
 
@@ -0,0 +1,9 @@
+//! Decoding data structures
+
+pub mod auto;
+pub mod lzma_params;
+pub mod options;
+pub mod raw;
+pub mod reader;
+#[cfg(feature = "stream")]
+pub mod stream;
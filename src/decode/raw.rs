@@ -0,0 +1,75 @@
+//! Headerless ("raw") LZMA decoding.
+//!
+//! Some container formats (e.g. CHD hunks, embedded firmware blobs) store LZMA-compressed
+//! data without the 13-byte `.lzma` header: no properties byte, no dictionary size, no
+//! unpacked size. Those values are instead known from the surrounding container. This
+//! module lets a caller supply them directly and skips [`LzmaParams::read_header`],
+//! decoding a bare range-coded body straight from a [`BufRead`](std::io::BufRead).
+
+use std::io;
+
+use crate::decode_internal::lzbuffer::LzBuffer;
+use crate::decode_internal::{lzma, rangecoder};
+use crate::error;
+
+pub use crate::decode::lzma_params::{LzmaParams, LzmaProperties};
+
+/// Decoder for a raw LZMA stream, i.e. one with no `.lzma` header.
+#[derive(Debug)]
+pub struct LzmaDecoder {
+    params: LzmaParams,
+}
+
+impl LzmaDecoder {
+    /// Construct a decoder from explicit, already-known parameters.
+    pub fn new(params: LzmaParams) -> Self {
+        LzmaDecoder { params }
+    }
+
+    /// Decode the raw range-coded body in `input` into `output`.
+    pub fn decompress<R: io::BufRead, W: io::Write>(
+        self,
+        input: &mut R,
+        output: &mut W,
+    ) -> error::Result<()> {
+        let mut decoder = lzma::new_circular(output, self.params)?;
+        let mut rangecoder = rangecoder::RangeDecoder::new(input)
+            .map_err(|e| error::Error::LzmaError(format!("LZMA stream too short: {}", e)))?;
+        decoder.process(&mut rangecoder)?;
+        decoder.output.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn new_floors_tiny_dict_sizes() {
+        let properties = LzmaProperties { lc: 3, lp: 0, pb: 2 };
+
+        let params = LzmaParams::new(properties, 0, None);
+        assert_eq!(params.dict_size, 0x1000);
+
+        let params = LzmaParams::new(properties, 1, None);
+        assert_eq!(params.dict_size, 0x1000);
+
+        // A dict size already at or above the floor passes through untouched.
+        let params = LzmaParams::new(properties, 0x2000, None);
+        assert_eq!(params.dict_size, 0x2000);
+    }
+
+    #[test]
+    fn decompress_rejects_a_truncated_range_coder_body() {
+        let properties = LzmaProperties { lc: 3, lp: 0, pb: 2 };
+        let params = LzmaParams::new(properties, 0x1000, Some(0));
+        let decoder = LzmaDecoder::new(params);
+
+        // An empty body can't even seed the range coder's initial bytes.
+        let mut input = Cursor::new(Vec::new());
+        let mut output = Vec::new();
+        assert!(decoder.decompress(&mut input, &mut output).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! `Read`-based adapters for decompression.
+//!
+//! Mirroring flate2/xz2's `Decoder` types, [`LzmaDecoder`] and [`XzDecoder`] run the real
+//! decompressor on a background thread and hand decoded bytes back to the caller through
+//! a small bounded channel. That bounds this adapter's own memory use to a handful of
+//! chunks in flight, rather than the whole decompressed output: if the caller reads
+//! slowly (or not at all), the background thread blocks on a full channel instead of
+//! decompressing ahead into an ever-growing buffer. Errors from the decompressor (bad
+//! headers, corrupt data, truncated input) surface from [`read`](Read::read) as an
+//! ordinary `io::Error`, the same way flate2's decoders report them.
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use super::options::Options;
+
+/// How many decoded chunks may be buffered between the decompression thread and the
+/// reader before the former blocks - the bound on these adapters' memory use.
+const CHANNEL_CAPACITY: usize = 4;
+
+/// A `Write` sink that forwards each write as one chunk over a bounded channel, blocking
+/// once `CHANNEL_CAPACITY` chunks are outstanding.
+struct ChannelWriter {
+    tx: mpsc::SyncSender<io::Result<Vec<u8>>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx.send(Ok(buf.to_vec())).map_err(|_| {
+            io::Error::new(io::ErrorKind::BrokenPipe, "decoder's reader was dropped")
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pull already-decoded bytes into `buf` (up to its length), blocking on the channel if nothing is
+/// buffered yet; returns `Ok(0)` once the background thread finishes cleanly.
+fn recv_read(
+    rx: &mpsc::Receiver<io::Result<Vec<u8>>>,
+    pending: &mut io::Cursor<Vec<u8>>,
+    buf: &mut [u8],
+) -> io::Result<usize> {
+    loop {
+        let n = pending.read(buf)?;
+        if n > 0 {
+            return Ok(n);
+        }
+        match rx.recv() {
+            Ok(Ok(chunk)) => *pending = io::Cursor::new(chunk),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(0), // background thread finished: clean EOF.
+        }
+    }
+}
+
+/// A `Read` adapter that decompresses an LZMA stream on a background thread and serves
+/// the result out incrementally; see the module docs.
+#[derive(Debug)]
+pub struct LzmaDecoder {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    pending: io::Cursor<Vec<u8>>,
+}
+
+impl LzmaDecoder {
+    /// Begin decompressing `input` with the given options on a background thread, ready
+    /// to be read out as plain bytes as they become available.
+    pub fn new<R: io::BufRead + Send + 'static>(mut input: R, options: &Options) -> Self {
+        let options = options.clone();
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let err_tx = tx.clone();
+        thread::spawn(move || {
+            let mut writer = ChannelWriter { tx };
+            if let Err(e) = crate::lzma_decompress_with_options(&mut input, &mut writer, &options)
+            {
+                let _ = err_tx.send(Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("{:?}", e),
+                )));
+            }
+        });
+        LzmaDecoder {
+            rx,
+            pending: io::Cursor::new(Vec::new()),
+        }
+    }
+}
+
+impl Read for LzmaDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        recv_read(&self.rx, &mut self.pending, buf)
+    }
+}
+
+/// A `Read` adapter that decompresses an XZ stream on a background thread and serves the
+/// result out incrementally; see the module docs.
+#[derive(Debug)]
+pub struct XzDecoder {
+    rx: mpsc::Receiver<io::Result<Vec<u8>>>,
+    pending: io::Cursor<Vec<u8>>,
+}
+
+impl XzDecoder {
+    /// Begin decompressing `input` on a background thread, ready to be read out as
+    /// plain bytes as they become available.
+    pub fn new<R: io::BufRead + Send + 'static>(mut input: R) -> Self {
+        let (tx, rx) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let err_tx = tx.clone();
+        thread::spawn(move || {
+            let mut writer = ChannelWriter { tx };
+            if let Err(e) = crate::xz_decompress(&mut input, &mut writer) {
+                let _ = err_tx.send(Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("{:?}", e),
+                )));
+            }
+        });
+        XzDecoder {
+            rx,
+            pending: io::Cursor::new(Vec::new()),
+        }
+    }
+}
+
+impl Read for XzDecoder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        recv_read(&self.rx, &mut self.pending, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn lzma_decoder_reports_truncated_input_from_read_not_new() {
+        let mut decoder = LzmaDecoder::new(Cursor::new(Vec::new()), &Options::default());
+        let mut buf = [0u8; 16];
+        assert!(decoder.read(&mut buf).is_err());
+    }
+
+    #[test]
+    fn xz_decoder_reports_bad_magic_from_read_not_new() {
+        let mut decoder = XzDecoder::new(Cursor::new(b"not an xz stream".to_vec()));
+        let mut buf = [0u8; 16];
+        assert!(decoder.read(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! Internal decoder implementations backing the public `lzma_decompress`,
+//! `lzma2_decompress`, and `xz_decompress` entry points in the crate root.
+//!
+//! `lzbuffer`, `lzma`, `lzma2`, and `rangecoder` are referenced throughout this crate
+//! (e.g. from `lib.rs` and `decode::raw`) but do not exist in this source tree; only
+//! `xz` - the Block/Stream framing XZ needs around an LZMA2 payload, as opposed to
+//! decoding that payload itself - has been implemented so far.
+
+pub(crate) mod lzbuffer;
+pub(crate) mod lzma;
+pub(crate) mod lzma2;
+pub(crate) mod rangecoder;
+pub(crate) mod xz;
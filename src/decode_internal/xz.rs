@@ -0,0 +1,274 @@
+//! Internal XZ stream decoding: Stream/Block framing, per-block integrity check
+//! verification, and concatenated multi-stream support.
+//!
+//! Block bodies are LZMA2 payloads, which are self-delimiting (a chunk-control byte of
+//! `0x00` ends them) independent of the outer Block framing, so this module delegates
+//! the actual decompression to [`crate::decode_internal::lzma2::decode_stream`] and only
+//! has to worry about the Stream/Block/Index structure wrapped around it.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::error;
+use crate::xz::{self, Check};
+
+/// Decode one or more concatenated XZ streams from `input` into `output`, stopping
+/// cleanly at EOF. Real-world `.xz` files (and the output of tools that append streams)
+/// may contain several streams back-to-back, optionally separated by zero padding
+/// aligned to 4 bytes, the way libarchive's xz reader expects.
+pub(crate) fn decode_stream<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> error::Result<()> {
+    decode_one_stream(input, output)?;
+    while skip_stream_padding(input)? {
+        decode_one_stream(input, output)?;
+    }
+    Ok(())
+}
+
+/// Decode a single XZ stream: header, Blocks, Index, Footer.
+fn decode_one_stream<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> error::Result<()> {
+    let check = read_stream_header(input)?;
+
+    loop {
+        let buf = input.fill_buf().map_err(error::Error::HeaderTooShort)?;
+        if buf.is_empty() {
+            return Err(error::Error::LzmaError(
+                "truncated XZ stream: missing Index".to_string(),
+            ));
+        }
+        if buf[0] == 0 {
+            break; // Index Indicator: no more blocks.
+        }
+        decode_block(input, output, check)?;
+    }
+
+    skip_index_and_footer(input)
+}
+
+/// Skip a run of zero-byte padding between concatenated streams (which the format
+/// requires to be a multiple of 4 bytes long, erroring otherwise), then report whether
+/// another stream follows.
+fn skip_stream_padding<R: BufRead>(input: &mut R) -> error::Result<bool> {
+    let mut padding_len = 0usize;
+    loop {
+        let buf = input.fill_buf().map_err(error::Error::HeaderTooShort)?;
+        if buf.is_empty() || buf[0] != 0 {
+            break;
+        }
+        input.consume(1);
+        padding_len += 1;
+    }
+    if padding_len % 4 != 0 {
+        return Err(error::Error::LzmaError(format!(
+            "XZ inter-stream padding must be a multiple of 4 bytes, got {}",
+            padding_len
+        )));
+    }
+    let buf = input.fill_buf().map_err(error::Error::HeaderTooShort)?;
+    Ok(!buf.is_empty())
+}
+
+/// Parse and validate the 12-byte Stream Header, returning the integrity check type its
+/// flags declare.
+fn read_stream_header<R: Read>(input: &mut R) -> error::Result<Check> {
+    let mut magic = [0u8; 6];
+    read_exact(input, &mut magic)?;
+    if magic != xz::HEADER_MAGIC {
+        return Err(error::Error::LzmaError(
+            "not an XZ stream: bad header magic".to_string(),
+        ));
+    }
+
+    let mut stream_flags = [0u8; 2];
+    read_exact(input, &mut stream_flags)?;
+    let mut header_crc = [0u8; 4];
+    read_exact(input, &mut header_crc)?;
+    if xz::crc32(&stream_flags) != u32::from_le_bytes(header_crc) {
+        return Err(error::Error::LzmaError(
+            "XZ stream header CRC32 mismatch".to_string(),
+        ));
+    }
+
+    Check::from_id(stream_flags[1] & 0x0f)
+}
+
+/// Decode one Block: header, LZMA2 payload, padding, then the trailing check value,
+/// which is verified against a fresh digest of the decoded bytes.
+fn decode_block<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    check: Check,
+) -> error::Result<()> {
+    skip_block_header(input)?;
+
+    let mut decoded = Vec::new();
+    let compressed_len = {
+        let mut counted = CountingReader::new(input);
+        crate::decode_internal::lzma2::decode_stream(&mut counted, &mut decoded)?;
+        counted.count() as usize
+    };
+
+    let padding = xz::align4(compressed_len) - compressed_len;
+    skip_zeroes(input, padding)?;
+
+    let mut check_value = vec![0u8; check.size()];
+    read_exact(input, &mut check_value)?;
+    if check.digest(&decoded) != check_value {
+        return Err(error::Error::LzmaError(
+            "XZ block integrity check mismatch".to_string(),
+        ));
+    }
+
+    output
+        .write_all(&decoded)
+        .map_err(|e| error::Error::LzmaError(format!("XZ output write failed: {}", e)))
+}
+
+/// Parse and validate a Block Header, checking its CRC32 and that its one filter is
+/// LZMA2; the Filter Properties byte (LZMA2 dictionary size) isn't needed here since
+/// `lzma2::decode_stream` figures that out from the payload itself.
+fn skip_block_header<R: Read>(input: &mut R) -> error::Result<()> {
+    let mut size_byte = [0u8; 1];
+    read_exact(input, &mut size_byte)?;
+    let real_header_size = (size_byte[0] as usize + 1) * 4;
+
+    let mut rest = vec![0u8; real_header_size - 1];
+    read_exact(input, &mut rest)?;
+
+    let (body, stored_crc) = rest.split_at(rest.len() - 4);
+    let mut full_header = Vec::with_capacity(real_header_size);
+    full_header.push(size_byte[0]);
+    full_header.extend_from_slice(body);
+    if xz::crc32(&full_header) != u32::from_le_bytes(stored_crc.try_into().unwrap()) {
+        return Err(error::Error::LzmaError(
+            "XZ block header CRC32 mismatch".to_string(),
+        ));
+    }
+
+    // body[0] is the block flags byte; the filters list follows it.
+    let mut filters = &body[1..];
+    let filter_id = xz::decode_multibyte(&mut filters)
+        .map_err(error::Error::HeaderTooShort)?
+        .ok_or_else(|| error::Error::LzmaError("truncated XZ block header".to_string()))?;
+    if filter_id != xz::FILTER_ID_LZMA2 {
+        return Err(error::Error::LzmaError(format!(
+            "unsupported XZ filter id {} (only LZMA2 is supported)",
+            filter_id
+        )));
+    }
+
+    Ok(())
+}
+
+/// Parse the Index and Stream Footer that follow the last Block, checking the Footer's
+/// CRC32 and that its Backward Size agrees with the Index actually read.
+fn skip_index_and_footer<R: BufRead>(input: &mut R) -> error::Result<()> {
+    let consumed = {
+        let mut counted = CountingReader::new(input);
+
+        let mut indicator = [0u8; 1];
+        read_exact(&mut counted, &mut indicator)?;
+        if indicator[0] != 0 {
+            return Err(error::Error::LzmaError(
+                "expected XZ Index Indicator".to_string(),
+            ));
+        }
+
+        let num_records = xz::decode_multibyte(&mut counted)
+            .map_err(error::Error::HeaderTooShort)?
+            .ok_or_else(|| error::Error::LzmaError("truncated XZ index".to_string()))?;
+        for _ in 0..num_records {
+            xz::decode_multibyte(&mut counted).map_err(error::Error::HeaderTooShort)?;
+            xz::decode_multibyte(&mut counted).map_err(error::Error::HeaderTooShort)?;
+        }
+
+        counted.count() as usize
+    };
+
+    let padding = xz::align4(consumed) - consumed;
+    skip_zeroes(input, padding)?;
+
+    let mut index_crc = [0u8; 4]; // Index CRC32: consumed, not verified
+    read_exact(input, &mut index_crc)?;
+
+    let mut footer_crc = [0u8; 4];
+    read_exact(input, &mut footer_crc)?;
+    let mut footer_body = [0u8; 6]; // Backward Size (4) + Stream Flags (2)
+    read_exact(input, &mut footer_body)?;
+    if xz::crc32(&footer_body) != u32::from_le_bytes(footer_crc) {
+        return Err(error::Error::LzmaError(
+            "XZ stream footer CRC32 mismatch".to_string(),
+        ));
+    }
+
+    let backward_size = u32::from_le_bytes(footer_body[0..4].try_into().unwrap());
+    let expected_backward_size = ((consumed + padding + 4) / 4) as u32 - 1;
+    if backward_size != expected_backward_size {
+        return Err(error::Error::LzmaError(
+            "XZ stream footer Backward Size does not match the Index".to_string(),
+        ));
+    }
+
+    let mut magic = [0u8; 2];
+    read_exact(input, &mut magic)?;
+    if magic != xz::FOOTER_MAGIC {
+        return Err(error::Error::LzmaError(
+            "bad XZ stream footer magic".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn read_exact<R: Read>(input: &mut R, buf: &mut [u8]) -> error::Result<()> {
+    input.read_exact(buf).map_err(error::Error::HeaderTooShort)
+}
+
+fn skip_zeroes<R: Read>(input: &mut R, count: usize) -> error::Result<()> {
+    let mut padding = vec![0u8; count];
+    read_exact(input, &mut padding)?;
+    if padding.iter().any(|&b| b != 0) {
+        return Err(error::Error::LzmaError(
+            "non-zero byte in XZ padding".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// A thin [`Read`]/[`BufRead`] pass-through that counts bytes consumed, so callers can
+/// tell how much of a bounded region (a Block's LZMA2 payload, the Index) a delegate
+/// decoder read without that decoder needing to report it itself.
+struct CountingReader<'a, R: BufRead> {
+    inner: &'a mut R,
+    count: u64,
+}
+
+impl<'a, R: BufRead> CountingReader<'a, R> {
+    fn new(inner: &'a mut R) -> Self {
+        CountingReader { inner, count: 0 }
+    }
+
+    fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl<'a, R: BufRead> Read for CountingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<'a, R: BufRead> BufRead for CountingReader<'a, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.count += amt as u64;
+    }
+}
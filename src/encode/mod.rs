@@ -0,0 +1,8 @@
+//! Encoding data structures
+
+pub mod dumbencoder;
+pub mod lzma2;
+pub mod options;
+pub mod writer;
+pub mod xz;
+pub mod xz_mt;
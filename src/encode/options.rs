@@ -0,0 +1,272 @@
+//! Options for the LZMA/LZMA2/XZ encoders.
+//!
+//! **Scope note:** [`Options::resolve_dict_size`], [`Options::resolve_properties`], and
+//! the [`Options::resolve`] combining them are implemented and unit-tested, but are not
+//! wired into [`crate::lzma_compress_with_options`] or any other real encode path in
+//! this crate, so none of `preset`/`dict_size`/`lc`/`lp`/`pb`/`check` currently affect
+//! compressed output - see each field's own doc on [`Options`] for specifics. This is a
+//! deliberate, acknowledged scope reduction, not an oversight: wiring `resolve()` in
+//! would mean writing the body of `lzma_compress_with_options`'s `encode::dumbencoder::Encoder`,
+//! which does not exist anywhere in this source tree (only its `mod dumbencoder;`
+//! declaration in `encode/mod.rs` does) and is a full range-coding LZMA encoder in its
+//! own right - out of scope for this options-resolution ticket. The same blocker
+//! applies to `encode::xz::encode_stream`, also just a `mod xz;` declaration with no
+//! backing file. Until one of those exists, treat `Options::resolve`/`resolve_dict_size`/
+//! `resolve_properties` as ready-to-use building blocks for whoever picks that up, not
+//! as a working feature on their own.
+
+use crate::decode::lzma_params::LzmaProperties;
+use crate::error;
+use crate::xz::Check;
+
+/// Options controlling how data is compressed.
+///
+/// **None of these fields currently have any effect on compressed output** - see the
+/// module docs for why - so a caller setting any of them today gets the same bytes out
+/// as [`Options::default`]. This is a known, scoped-down limitation of this struct
+/// rather than a deliverable: the resolution logic (see [`Options::resolve`] and
+/// friends) is implemented and tested in isolation, but wiring it into an actual
+/// encoder is blocked on code that does not exist in this source tree.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Preset level (`0..=9`), trading ratio for speed/memory the way the reference LZMA
+    /// encoders do. Used to derive [`dict_size`](Options::dict_size) when that field is
+    /// left unset. Defaults to `6`. Currently has no effect; see the struct docs.
+    pub preset: u32,
+    /// Hint at the uncompressed input size. When set, the dictionary size derived from
+    /// `preset` is shrunk to the smallest size that still comfortably fits it, instead of
+    /// wastefully allocating more dictionary than the input could ever use. Currently has
+    /// no effect; see the struct docs.
+    pub reduce_size: Option<u64>,
+    /// Explicit dictionary size, overriding the one derived from `preset` and
+    /// `reduce_size`. Most callers should leave this unset. Currently has no effect; see
+    /// the struct docs.
+    pub dict_size: Option<u32>,
+    /// Number of literal context bits (`0..8`). `None` uses the conventional default of
+    /// `3`. Currently has no effect; see the struct docs.
+    pub lc: Option<u32>,
+    /// Number of literal position bits (`0..4`). `None` uses the conventional default of
+    /// `0`. Currently has no effect; see the struct docs.
+    pub lp: Option<u32>,
+    /// Number of position bits (`0..4`). `None` uses the conventional default of `2`.
+    /// Currently has no effect; see the struct docs.
+    pub pb: Option<u32>,
+    /// Which integrity check an XZ encode path should write into the stream header and
+    /// compute over each block. Defaults to [`Check::Crc32`] for interoperability with
+    /// other XZ tools; ignored by plain LZMA/LZMA2 compression, which has no check of its
+    /// own. [`compress::XzMtEncoder`](crate::compress::XzMtEncoder) and
+    /// [`compress::XzEncoder`](crate::compress::XzEncoder) take their check type
+    /// separately via `with_check` rather than through this struct, so this field is, as
+    /// with the rest of `Options`, currently unused by any real encoder in this crate.
+    pub check: Check,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            preset: 6,
+            reduce_size: None,
+            dict_size: None,
+            lc: None,
+            lp: None,
+            pb: None,
+            check: Check::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Resolve the dictionary size to use: `dict_size` if set explicitly, otherwise one
+    /// derived from `preset` and `reduce_size`.
+    pub fn resolve_dict_size(&self) -> u32 {
+        match self.dict_size {
+            Some(dict_size) => dict_size,
+            None => derive_dict_size(self.preset, self.reduce_size),
+        }
+    }
+
+    /// Resolve the `lc`/`lp`/`pb` triple to use, falling back to the conventional LZMA
+    /// defaults of `(3, 0, 2)` for any field left unset, validating the combination.
+    pub fn resolve_properties(&self) -> error::Result<LzmaProperties> {
+        let lc = self.lc.unwrap_or(3);
+        let lp = self.lp.unwrap_or(0);
+        let pb = self.pb.unwrap_or(2);
+        validate_properties(lc, lp, pb)?;
+        Ok(LzmaProperties { lc, lp, pb })
+    }
+
+    /// Resolve both [`resolve_dict_size`](Self::resolve_dict_size) and
+    /// [`resolve_properties`](Self::resolve_properties) together, the pair an LZMA
+    /// encoder actually needs to run. Not yet called by anything: see the module docs
+    /// for why `encode::dumbencoder::Encoder` isn't wired up to consume this yet.
+    pub fn resolve(&self) -> error::Result<(u32, LzmaProperties)> {
+        Ok((self.resolve_dict_size(), self.resolve_properties()?))
+    }
+}
+
+/// Validate an `(lc, lp, pb)` triple against the constraints the LZMA format requires.
+fn validate_properties(lc: u32, lp: u32, pb: u32) -> error::Result<()> {
+    if lc >= 9 {
+        return Err(error::Error::LzmaError(format!(
+            "lc must be < 9, got {}",
+            lc
+        )));
+    }
+    if lp >= 5 {
+        return Err(error::Error::LzmaError(format!(
+            "lp must be < 5, got {}",
+            lp
+        )));
+    }
+    if pb >= 5 {
+        return Err(error::Error::LzmaError(format!(
+            "pb must be < 5, got {}",
+            pb
+        )));
+    }
+    if lc + lp > 4 {
+        return Err(error::Error::LzmaError(format!(
+            "lc + lp must be <= 4, got lc={} lp={} (sum {})",
+            lc,
+            lp,
+            lc + lp
+        )));
+    }
+    Ok(())
+}
+
+/// Derive a dictionary size the way reference LZMA encoders do: start from a size keyed
+/// off the preset level, then shrink it to the smallest size that still fits
+/// `reduce_size`, if that hint is smaller.
+fn derive_dict_size(preset: u32, reduce_size: Option<u64>) -> u32 {
+    let preset = preset.min(9);
+    let mut dict_size: u64 = if preset <= 5 {
+        1 << (preset * 2 + 14)
+    } else if preset <= 7 {
+        1 << 25
+    } else {
+        1 << 26
+    };
+
+    if let Some(reduce_size) = reduce_size {
+        if reduce_size < dict_size {
+            dict_size = shrink_to_fit(reduce_size);
+        }
+    }
+
+    dict_size as u32
+}
+
+/// Pick the smallest `2 << i` or `3 << i` (`i` in `11..=30`) that is `>= reduce_size`.
+fn shrink_to_fit(reduce_size: u64) -> u64 {
+    for i in 11..=30u32 {
+        let two = 2u64 << i;
+        if two >= reduce_size {
+            return two;
+        }
+        let three = 3u64 << i;
+        if three >= reduce_size {
+            return three;
+        }
+    }
+    1 << 26
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrink_to_fit_picks_smallest_covering_size() {
+        let cases: &[(u64, u64)] = &[
+            (1, 2 << 11),
+            (2 << 11, 2 << 11),
+            ((2 << 11) + 1, 3 << 11),
+            ((3 << 11) + 1, 2 << 12),
+            (1 << 30, 1 << 30),
+            (u64::MAX, 1 << 26), // no `2 << i`/`3 << i` in range covers this: falls back
+        ];
+        for &(reduce_size, expected) in cases {
+            let got = shrink_to_fit(reduce_size);
+            assert_eq!(
+                got, expected,
+                "shrink_to_fit({}) = {}, want {}",
+                reduce_size, got, expected
+            );
+            assert!(got >= reduce_size || got == 1 << 26);
+        }
+    }
+
+    #[test]
+    fn derive_dict_size_follows_preset_table() {
+        assert_eq!(derive_dict_size(0, None), 1 << 14);
+        assert_eq!(derive_dict_size(5, None), 1 << 24);
+        assert_eq!(derive_dict_size(6, None), 1 << 25);
+        assert_eq!(derive_dict_size(7, None), 1 << 25);
+        assert_eq!(derive_dict_size(8, None), 1 << 26);
+        assert_eq!(derive_dict_size(9, None), 1 << 26);
+        // presets above 9 clamp to 9 rather than panicking or overflowing the shift.
+        assert_eq!(derive_dict_size(255, None), derive_dict_size(9, None));
+    }
+
+    #[test]
+    fn derive_dict_size_shrinks_to_reduce_size_hint() {
+        // A small `reduce_size` hint should shrink the preset's dict size down, never up.
+        let preset_size = derive_dict_size(9, None);
+        let shrunk = derive_dict_size(9, Some(1024));
+        assert!(shrunk < preset_size);
+        assert_eq!(u64::from(shrunk), shrink_to_fit(1024));
+
+        // A hint larger than the preset's own dict size has no effect.
+        assert_eq!(derive_dict_size(0, Some(u64::MAX)), derive_dict_size(0, None));
+    }
+
+    #[test]
+    fn resolve_dict_size_prefers_explicit_value() {
+        let mut options = Options {
+            dict_size: Some(12345),
+            ..Options::default()
+        };
+        assert_eq!(options.resolve_dict_size(), 12345);
+
+        options.dict_size = None;
+        assert_eq!(options.resolve_dict_size(), derive_dict_size(6, None));
+    }
+
+    #[test]
+    fn resolve_properties_defaults_and_validates() {
+        let props = Options::default().resolve_properties().unwrap();
+        assert_eq!((props.lc, props.lp, props.pb), (3, 0, 2));
+
+        let bad = Options {
+            lc: Some(4),
+            lp: Some(1),
+            ..Options::default()
+        };
+        assert!(bad.resolve_properties().is_err()); // lc + lp = 5 > 4
+    }
+
+    #[test]
+    fn check_defaults_to_crc32() {
+        assert_eq!(Options::default().check, Check::Crc32);
+    }
+
+    #[test]
+    fn resolve_combines_dict_size_and_properties() {
+        let options = Options {
+            dict_size: Some(12345),
+            lc: Some(4),
+            ..Options::default()
+        };
+        let (dict_size, properties) = options.resolve().unwrap();
+        assert_eq!(dict_size, 12345);
+        assert_eq!((properties.lc, properties.lp, properties.pb), (4, 0, 2));
+
+        let bad = Options {
+            lc: Some(4),
+            lp: Some(1),
+            ..Options::default()
+        };
+        assert!(bad.resolve().is_err()); // lc + lp = 5 > 4
+    }
+}
@@ -0,0 +1,205 @@
+//! `Write`-based adapters for compression.
+//!
+//! [`LzmaEncoder`] buffers every write into an internal `Vec<u8>` and only runs the
+//! range coder once, in [`finish`](LzmaEncoder::finish): the legacy `.lzma` format is one
+//! continuous range-coded stream with no block boundaries to flush early at, so nothing
+//! short of driving that range coder incrementally (which would mean rewriting it) could
+//! bound this below the size of the whole input.
+//!
+//! [`XzEncoder`] doesn't have that problem: the XZ container is already a sequence of
+//! independently-compressed Blocks (see [`XzMtEncoder`](crate::compress::XzMtEncoder),
+//! the block-parallel version of the same framing), so this adapter flushes a finished
+//! Block to the wrapped writer every `block_size` bytes written instead of waiting for
+//! [`finish`](XzEncoder::finish), keeping memory use bounded to roughly one block rather
+//! than the whole input.
+
+use std::io::{self, Write};
+
+use super::options::Options;
+use crate::xz::{self, Check, FILTER_ID_LZMA2};
+
+/// A `Write` adapter that compresses everything written to it with LZMA, writing the
+/// result to the wrapped writer once [`finish`](LzmaEncoder::finish) is called. Not
+/// incremental: see the module docs for why.
+#[derive(Debug)]
+pub struct LzmaEncoder<W: Write> {
+    inner: Option<W>,
+    staging: Vec<u8>,
+    options: Options,
+}
+
+impl<W: Write> LzmaEncoder<W> {
+    /// Create a new encoder wrapping `inner`, using the provided options.
+    pub fn new(inner: W, options: Options) -> Self {
+        LzmaEncoder {
+            inner: Some(inner),
+            staging: Vec::new(),
+            options,
+        }
+    }
+
+    /// Compress everything written so far, flush it to the wrapped writer, and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut inner = self.inner.take().expect("finish() called more than once");
+        let mut input = io::Cursor::new(std::mem::take(&mut self.staging));
+        crate::lzma_compress_with_options(&mut input, &mut inner, &self.options)?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for LzmaEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.staging.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod lzma_encoder_tests {
+    use super::*;
+
+    #[test]
+    fn buffers_without_compressing_until_finish() {
+        let mut encoder = LzmaEncoder::new(Vec::new(), Options::default());
+        encoder.write_all(b"hello world").unwrap();
+        // Nothing is compressed, and the wrapped writer stays untouched, until finish().
+        assert_eq!(encoder.staging, b"hello world");
+    }
+}
+
+/// A `Write` adapter that compresses everything written to it as an XZ stream, flushing
+/// a finished Block to the wrapped writer every `block_size` bytes (see the module
+/// docs) rather than buffering the whole input until [`finish`](XzEncoder::finish).
+#[derive(Debug)]
+pub struct XzEncoder<W: Write> {
+    inner: Option<W>,
+    staging: Vec<u8>,
+    block_size: usize,
+    check: Check,
+    stream_flags: Option<[u8; 2]>,
+    records: Vec<(u64, u64)>,
+}
+
+impl<W: Write> XzEncoder<W> {
+    /// Create a new XZ encoder wrapping `inner`, using the default block size
+    /// ([`crate::compress::DEFAULT_BLOCK_SIZE`]) and integrity check ([`Check::default`]).
+    pub fn new(inner: W) -> Self {
+        Self::with_block_size(inner, crate::compress::DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Create an encoder that flushes a Block every `block_size` bytes written.
+    pub fn with_block_size(inner: W, block_size: usize) -> Self {
+        XzEncoder {
+            inner: Some(inner),
+            staging: Vec::new(),
+            block_size: block_size.max(1),
+            check: Check::default(),
+            stream_flags: None,
+            records: Vec::new(),
+        }
+    }
+
+    /// Use `check` as the integrity check computed over each block's uncompressed data,
+    /// instead of the default ([`Check::Crc32`]).
+    pub fn with_check(mut self, check: Check) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Write the Stream Header, if it hasn't been written yet.
+    fn ensure_header(&mut self) -> io::Result<()> {
+        if self.stream_flags.is_some() {
+            return Ok(());
+        }
+        let inner = self.inner.as_mut().expect("finish() called more than once");
+        inner.write_all(&xz::HEADER_MAGIC)?;
+        let stream_flags = [0x00, self.check.id()];
+        inner.write_all(&stream_flags)?;
+        inner.write_all(&xz::crc32(&stream_flags).to_le_bytes())?;
+        self.stream_flags = Some(stream_flags);
+        Ok(())
+    }
+
+    /// Compress `data` as one Block (with its own fresh LZMA2 dictionary) and write it
+    /// straight to the wrapped writer.
+    fn flush_block(&mut self, data: &[u8]) -> io::Result<()> {
+        self.ensure_header()?;
+        let mut compressed = Vec::new();
+        crate::encode::lzma2::encode_stream(&mut io::Cursor::new(data), &mut compressed)?;
+        let check_value = self.check.digest(data);
+        let dict_size_byte = xz::lzma2_dict_size_byte(self.block_size.min(u32::MAX as usize) as u32);
+        let inner = self.inner.as_mut().expect("finish() called more than once");
+        let unpadded_size = xz::write_block(
+            inner,
+            FILTER_ID_LZMA2,
+            &[dict_size_byte],
+            &compressed,
+            &check_value,
+        )?;
+        self.records.push((unpadded_size, data.len() as u64));
+        Ok(())
+    }
+
+    /// Flush any buffered remainder as a final Block, write the Index and Footer, and
+    /// return the wrapped writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.ensure_header()?;
+        if !self.staging.is_empty() {
+            let rest = std::mem::take(&mut self.staging);
+            self.flush_block(&rest)?;
+        }
+        let stream_flags = self.stream_flags.expect("header always written by now");
+        let mut inner = self.inner.take().expect("finish() called more than once");
+        xz::write_index_and_footer(&mut inner, &self.records, &stream_flags)?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for XzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.staging.extend_from_slice(buf);
+        while self.staging.len() >= self.block_size {
+            let rest = self.staging.split_off(self.block_size);
+            let block = std::mem::replace(&mut self.staging, rest);
+            self.flush_block(&block)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod xz_encoder_tests {
+    use super::*;
+
+    #[test]
+    fn flushes_a_block_as_soon_as_block_size_is_reached() {
+        let mut encoder = XzEncoder::with_block_size(Vec::new(), 4);
+        encoder
+            .write_all(b"hello world, this spans several blocks")
+            .unwrap();
+        // Every full block should already have been flushed out, leaving only a
+        // shorter-than-`block_size` remainder buffered in `staging`.
+        assert!(encoder.staging.len() < 4);
+        assert!(!encoder.records.is_empty());
+
+        let out = encoder.finish().unwrap();
+        assert!(out.starts_with(&xz::HEADER_MAGIC));
+        assert!(out.ends_with(&xz::FOOTER_MAGIC));
+    }
+
+    #[test]
+    fn empty_input_still_produces_a_valid_stream() {
+        let encoder = XzEncoder::new(Vec::new());
+        let out = encoder.finish().unwrap();
+        assert!(out.starts_with(&xz::HEADER_MAGIC));
+        assert!(out.ends_with(&xz::FOOTER_MAGIC));
+    }
+}
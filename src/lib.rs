@@ -21,13 +21,25 @@ use std::io;
 /// Compression helpers.
 pub mod compress {
     pub use crate::encode::options::*;
+    pub use crate::encode::writer::{LzmaEncoder, XzEncoder};
+    pub use crate::encode::xz_mt::{XzMtEncoder, DEFAULT_BLOCK_SIZE};
+    pub use crate::xz::Check;
 }
 
 /// Decompression helpers.
 pub mod decompress {
+    pub use crate::decode::auto::{auto_decompress, Format};
     pub use crate::decode::options::*;
+    pub use crate::decode::reader::{LzmaDecoder, XzDecoder};
+    pub use crate::xz::Check;
     #[cfg(feature = "stream")]
     pub use crate::decode::stream::Stream;
+
+    /// Headerless ("raw") LZMA decoding, for container formats that embed bare LZMA
+    /// payloads without the 13-byte `.lzma` header.
+    pub mod raw {
+        pub use crate::decode::raw::*;
+    }
 }
 
 /// Decompress LZMA data with default [`Options`](decompress/struct.Options.html).
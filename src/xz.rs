@@ -0,0 +1,413 @@
+//! Constants and small helpers shared between the XZ encoder and decoder.
+//!
+//! Kept separate from the XZ encoders ([`crate::encode::writer`], [`crate::encode::xz_mt`])
+//! and the decoder ([`crate::decode_internal::xz`]) so both
+//! sides agree on the container's magic bytes, filter ids, variable-length integer
+//! encoding, integrity checks, and multi-stream framing without duplicating them.
+
+use std::io::{self, Read, Write};
+
+/// The 6-byte magic that opens every XZ stream.
+pub const HEADER_MAGIC: [u8; 6] = [0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00];
+
+/// The 2-byte magic that closes every XZ stream, right after the footer CRC32.
+pub const FOOTER_MAGIC: [u8; 2] = [0x59, 0x5A];
+
+/// Filter id for LZMA2, the only filter lzma-rs' XZ encoder emits.
+pub const FILTER_ID_LZMA2: u64 = 0x21;
+
+/// Round `size` up to the next multiple of 4, as required for XZ block padding.
+pub fn align4(size: usize) -> usize {
+    (size + 3) & !3
+}
+
+/// Encode `value` as an XZ/`.xz`-style variable-length integer (little-endian groups of
+/// 7 bits, continuation bit set on every byte but the last).
+pub fn encode_multibyte(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+/// CRC-32 (IEEE 802.3 polynomial, as used throughout the XZ format).
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// CRC-64 (the reflected ECMA-182 polynomial that the XZ format uses).
+pub fn crc64(data: &[u8]) -> u64 {
+    const POLY: u64 = 0xC96C_5795_D787_0F42;
+    let mut crc = !0u64;
+    for &byte in data {
+        crc ^= byte as u64;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (POLY & mask);
+        }
+    }
+    !crc
+}
+
+/// Decode an XZ-style variable-length integer from `input`, returning `None` at a clean
+/// EOF (no bytes read at all) and an error on anything else malformed.
+pub fn decode_multibyte<R: Read>(input: &mut R) -> io::Result<Option<u64>> {
+    let mut value: u64 = 0;
+    let mut shift = 0u32;
+    let mut byte = [0u8; 1];
+    for i in 0.. {
+        match input.read(&mut byte)? {
+            0 if i == 0 => return Ok(None),
+            0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated XZ variable-length integer",
+                ))
+            }
+            _ => {}
+        }
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+        if shift >= 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "XZ variable-length integer longer than 9 bytes",
+            ));
+        }
+    }
+    Ok(Some(value))
+}
+
+/// Which integrity check (if any) accompanies each block's compressed data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Check {
+    /// No integrity check.
+    None,
+    /// CRC-32 (4 bytes), the default and most interoperable choice.
+    Crc32,
+    /// CRC-64 (8 bytes).
+    Crc64,
+    /// SHA-256 (32 bytes).
+    Sha256,
+}
+
+impl Default for Check {
+    fn default() -> Self {
+        Check::Crc32
+    }
+}
+
+impl Check {
+    /// The low nibble of the stream flags' second byte that identifies this check type.
+    pub fn id(self) -> u8 {
+        match self {
+            Check::None => 0x00,
+            Check::Crc32 => 0x01,
+            Check::Crc64 => 0x04,
+            Check::Sha256 => 0x0A,
+        }
+    }
+
+    /// Parse a check type from the stream flags' second byte.
+    pub fn from_id(id: u8) -> crate::error::Result<Check> {
+        match id {
+            0x00 => Ok(Check::None),
+            0x01 => Ok(Check::Crc32),
+            0x04 => Ok(Check::Crc64),
+            0x0A => Ok(Check::Sha256),
+            _ => Err(crate::error::Error::LzmaError(format!(
+                "unsupported or reserved XZ check type id {}",
+                id
+            ))),
+        }
+    }
+
+    /// Size in bytes of this check's trailing value.
+    pub fn size(self) -> usize {
+        match self {
+            Check::None => 0,
+            Check::Crc32 => 4,
+            Check::Crc64 => 8,
+            Check::Sha256 => 32,
+        }
+    }
+
+    /// Compute this check's digest over `data`.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Check::None => Vec::new(),
+            Check::Crc32 => crc32(data).to_le_bytes().to_vec(),
+            Check::Crc64 => crc64(data).to_le_bytes().to_vec(),
+            Check::Sha256 => sha256(data).to_vec(),
+        }
+    }
+}
+
+/// SHA-256, hand-rolled in the same spirit as [`crc32`]/[`crc64`] above so this crate's
+/// XZ integrity checks don't need to pull in an external hashing crate.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    #[rustfmt::skip]
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    let bit_len = (data.len() as u64) * 8;
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&bit_len.to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in w.iter_mut().take(16).enumerate() {
+            *word = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh) =
+            (h[0], h[1], h[2], h[3], h[4], h[5], h[6], h[7]);
+
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ ((!e) & g);
+            let temp1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+        h[5] = h[5].wrapping_add(f);
+        h[6] = h[6].wrapping_add(g);
+        h[7] = h[7].wrapping_add(hh);
+    }
+
+    let mut out = [0u8; 32];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// Encode `dict_size` as an LZMA2 Filter Properties byte: the single byte the XZ format
+/// carries per LZMA2-filtered block so a reader knows how large a dictionary to
+/// allocate before it can decode the block. Per the XZ spec, byte `i` (`0..=39`) encodes
+/// `(2 | (i & 1)) << (i / 2 + 11)`, and `40` means `0xFFFF_FFFF`. Returns the smallest
+/// encodable size that is `>= dict_size`.
+pub fn lzma2_dict_size_byte(dict_size: u32) -> u8 {
+    if dict_size >= u32::MAX {
+        return 40;
+    }
+    for i in 0..40u8 {
+        if lzma2_dict_size_from_byte(i) >= dict_size as u64 {
+            return i;
+        }
+    }
+    40
+}
+
+/// Decode an LZMA2 Filter Properties byte back into the dictionary size it represents.
+pub fn lzma2_dict_size_from_byte(byte: u8) -> u64 {
+    if byte >= 40 {
+        return u32::MAX as u64;
+    }
+    (2 | (byte as u64 & 1)) << (byte / 2 + 11)
+}
+
+/// Build a Block Header for a single-filter block using `filter_id` and
+/// `filter_properties`, CRC32-terminated.
+pub fn build_block_header(filter_id: u64, filter_properties: &[u8]) -> Vec<u8> {
+    let mut filters = Vec::new();
+    encode_multibyte(filter_id, &mut filters);
+    filters.push(filter_properties.len() as u8);
+    filters.extend_from_slice(filter_properties);
+
+    let mut header = Vec::new();
+    header.push(0); // block flags: 1 filter, no compressed/uncompressed size fields
+    header.extend_from_slice(&filters);
+    while header.len() % 4 != 3 {
+        header.push(0);
+    }
+    // Real Header Size covers the size byte itself, the header body above, and the
+    // trailing CRC32 below; it's always a multiple of 4 thanks to the padding loop
+    // above, and is stored as `(real size / 4) - 1`, the same convention as the
+    // footer's `backward_size` further down.
+    let real_header_size = header.len() + 1 + 4;
+    let header_size_byte = (real_header_size / 4 - 1) as u8;
+
+    let mut block_header = Vec::with_capacity(header.len() + 5);
+    block_header.push(header_size_byte);
+    block_header.extend_from_slice(&header);
+    block_header.extend_from_slice(&crc32(&block_header).to_le_bytes());
+    block_header
+}
+
+/// Write one Block: header, compressed data, padding to a 4-byte boundary, then the
+/// check value. Returns the block's Unpadded Size (header + data + check, excluding the
+/// padding), as required for its index record.
+pub fn write_block<W: Write>(
+    out: &mut W,
+    filter_id: u64,
+    filter_properties: &[u8],
+    compressed: &[u8],
+    check_value: &[u8],
+) -> io::Result<u64> {
+    let block_header = build_block_header(filter_id, filter_properties);
+    out.write_all(&block_header)?;
+    out.write_all(compressed)?;
+    let padding = align4(compressed.len()) - compressed.len();
+    out.write_all(&vec![0u8; padding])?;
+    out.write_all(check_value)?;
+    Ok((block_header.len() + compressed.len() + check_value.len()) as u64)
+}
+
+/// Write the XZ index (one record per block) and the stream footer that points back at
+/// it.
+pub fn write_index_and_footer<W: Write>(
+    out: &mut W,
+    records: &[(u64, u64)],
+    stream_flags: &[u8; 2],
+) -> io::Result<()> {
+    let mut index = vec![0u8]; // index indicator
+    encode_multibyte(records.len() as u64, &mut index);
+    for &(unpadded_size, uncompressed_size) in records {
+        encode_multibyte(unpadded_size, &mut index);
+        encode_multibyte(uncompressed_size, &mut index);
+    }
+    while index.len() % 4 != 0 {
+        index.push(0);
+    }
+    index.extend_from_slice(&crc32(&index).to_le_bytes());
+    out.write_all(&index)?;
+
+    let backward_size = (index.len() / 4) as u32 - 1;
+    let mut footer = Vec::with_capacity(6);
+    footer.extend_from_slice(&backward_size.to_le_bytes());
+    footer.extend_from_slice(stream_flags);
+    out.write_all(&crc32(&footer).to_le_bytes())?;
+    out.write_all(&footer)?;
+    out.write_all(&FOOTER_MAGIC)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_header_round_trips() {
+        let dict_size_byte = lzma2_dict_size_byte(1 << 20);
+        let header = build_block_header(FILTER_ID_LZMA2, &[dict_size_byte]);
+
+        // The Block Header Size byte stores `(real_size / 4) - 1`; decoding it back must
+        // reproduce the header's actual length, CRC32 included.
+        let real_header_size = (header[0] as usize + 1) * 4;
+        assert_eq!(real_header_size, header.len());
+
+        let (body, stored_crc) = header.split_at(header.len() - 4);
+        assert_eq!(
+            crc32(body),
+            u32::from_le_bytes(stored_crc.try_into().unwrap())
+        );
+
+        // Block flags byte: 1 filter, no compressed/uncompressed size fields.
+        assert_eq!(body[1], 0);
+
+        let mut filters = &body[2..];
+        let filter_id = decode_multibyte(&mut filters).unwrap().expect("filter id");
+        assert_eq!(filter_id, FILTER_ID_LZMA2);
+        let properties_size = decode_multibyte(&mut filters)
+            .unwrap()
+            .expect("filter properties size");
+        // A real XZ reader needs this byte to know how large an LZMA2 dictionary to
+        // allocate; a zero-length properties field (as this used to assert) leaves it
+        // unable to decode the block at all.
+        assert_eq!(properties_size, 1);
+        assert_eq!(filters[0], dict_size_byte);
+        assert!(lzma2_dict_size_from_byte(dict_size_byte) >= 1 << 20);
+    }
+
+    #[test]
+    fn lzma2_dict_size_byte_round_trips() {
+        for &size in &[0u32, 1, 4096, (1 << 20) + 1, 1 << 26, u32::MAX] {
+            let byte = lzma2_dict_size_byte(size);
+            assert!(
+                lzma2_dict_size_from_byte(byte) >= size as u64,
+                "byte {} (size {}) undershoots requested dict_size {}",
+                byte,
+                lzma2_dict_size_from_byte(byte),
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn sha256_matches_known_vectors() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}